@@ -3,7 +3,10 @@ extern crate rocket;
 
 use blake3::Hasher;
 use fast_image_resize::{FilterType, ResizeAlg, ResizeOptions, Resizer};
-use image::{DynamicImage, ExtendedColorType, ImageEncoder, codecs::avif::AvifEncoder};
+use image::{
+    DynamicImage, ExtendedColorType, ImageEncoder,
+    codecs::{avif::AvifEncoder, jpeg::JpegEncoder},
+};
 use jpegxl_rs::encode::EncoderSpeed;
 use jpegxl_rs::encoder_builder;
 use libheif_rs::{
@@ -13,26 +16,146 @@ use rapid_qoi::{Colors, Qoi};
 use reqwest::Client;
 use rocket::{
     data::{Data, ToByteUnit},
-    http::{Status, uri::Origin},
-    request::Request,
+    http::{ContentType, Status, uri::Origin},
+    request::{self, FromRequest, Request},
     response::{Responder, Response},
     serde::json::{Value as SerdeJsonValue, json},
     tokio::task,
 };
 use serde::Deserialize;
 use std::{
+    collections::HashMap,
     fmt,
     fs,
     io::{Cursor, Write}, // Import the `Write` trait
     path::{Path, PathBuf},
+    sync::{
+        Mutex, OnceLock,
+        atomic::{AtomicU64, Ordering},
+    },
     time::Instant,
 };
-use tabled::{Table, Tabled};
 
-#[derive(Tabled)]
-struct PerformanceMetrics {
-    step: &'static str,
-    duration_ms: f64,
+/// Bucket boundaries (in milliseconds) for the `/metrics` stage-duration
+/// histogram. Mirrors the cheap-to-expensive spread of the pipeline's
+/// stages, from a cache-hash lookup to a full encode.
+const STAGE_DURATION_BUCKETS_MS: &[f64] = &[
+    1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0,
+];
+
+#[derive(Default)]
+struct StageHistogram {
+    /// One counter per bucket in `STAGE_DURATION_BUCKETS_MS`, plus a
+    /// trailing `+Inf` bucket; counts are cumulative ("<=" semantics).
+    bucket_counts: Vec<u64>,
+    sum_ms: f64,
+    count: u64,
+}
+
+impl StageHistogram {
+    fn observe(&mut self, duration_ms: f64) {
+        if self.bucket_counts.is_empty() {
+            self.bucket_counts = vec![0; STAGE_DURATION_BUCKETS_MS.len() + 1];
+        }
+        for (index, &bound) in STAGE_DURATION_BUCKETS_MS.iter().enumerate() {
+            if duration_ms <= bound {
+                self.bucket_counts[index] += 1;
+            }
+        }
+        *self.bucket_counts.last_mut().unwrap() += 1;
+        self.sum_ms += duration_ms;
+        self.count += 1;
+    }
+}
+
+/// Process-wide counters and histograms scraped by `GET /metrics`.
+#[derive(Default)]
+struct Metrics {
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    encodes_by_format: Mutex<HashMap<String, u64>>,
+    stage_durations: Mutex<HashMap<&'static str, StageHistogram>>,
+}
+
+impl Metrics {
+    fn record_cache_hit(&self) {
+        self.cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_cache_miss(&self) {
+        self.cache_misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_encode(&self, format: &str) {
+        let mut encodes = self.encodes_by_format.lock().unwrap();
+        *encodes.entry(format.to_string()).or_insert(0) += 1;
+    }
+
+    fn record_stage_duration(&self, stage: &'static str, duration_ms: f64) {
+        let mut stages = self.stage_durations.lock().unwrap();
+        stages.entry(stage).or_default().observe(duration_ms);
+    }
+
+    /// Renders all counters and histograms as Prometheus text exposition
+    /// format (https://prometheus.io/docs/instrumenting/exposition_formats/).
+    fn render_prometheus(&self) -> String {
+        let mut output = String::new();
+
+        output.push_str("# HELP fih_cache_hits_total Transform requests served from the images/ cache.\n");
+        output.push_str("# TYPE fih_cache_hits_total counter\n");
+        output.push_str(&format!(
+            "fih_cache_hits_total {}\n",
+            self.cache_hits.load(Ordering::Relaxed)
+        ));
+
+        output.push_str("# HELP fih_cache_misses_total Transform requests that required re-processing.\n");
+        output.push_str("# TYPE fih_cache_misses_total counter\n");
+        output.push_str(&format!(
+            "fih_cache_misses_total {}\n",
+            self.cache_misses.load(Ordering::Relaxed)
+        ));
+
+        output.push_str("# HELP fih_encodes_total Images encoded, by output format.\n");
+        output.push_str("# TYPE fih_encodes_total counter\n");
+        for (format, count) in self.encodes_by_format.lock().unwrap().iter() {
+            output.push_str(&format!("fih_encodes_total{{format=\"{format}\"}} {count}\n"));
+        }
+
+        output.push_str("# HELP fih_stage_duration_milliseconds Per-stage pipeline duration.\n");
+        output.push_str("# TYPE fih_stage_duration_milliseconds histogram\n");
+        for (stage, histogram) in self.stage_durations.lock().unwrap().iter() {
+            for (index, &bound) in STAGE_DURATION_BUCKETS_MS.iter().enumerate() {
+                output.push_str(&format!(
+                    "fih_stage_duration_milliseconds_bucket{{stage=\"{stage}\",le=\"{bound}\"}} {}\n",
+                    histogram.bucket_counts[index]
+                ));
+            }
+            output.push_str(&format!(
+                "fih_stage_duration_milliseconds_bucket{{stage=\"{stage}\",le=\"+Inf\"}} {}\n",
+                histogram.bucket_counts[STAGE_DURATION_BUCKETS_MS.len()]
+            ));
+            output.push_str(&format!(
+                "fih_stage_duration_milliseconds_sum{{stage=\"{stage}\"}} {}\n",
+                histogram.sum_ms
+            ));
+            output.push_str(&format!(
+                "fih_stage_duration_milliseconds_count{{stage=\"{stage}\"}} {}\n",
+                histogram.count
+            ));
+        }
+
+        output
+    }
+}
+
+fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::default)
+}
+
+#[get("/metrics")]
+fn metrics_handler() -> (ContentType, String) {
+    (ContentType::Plain, metrics().render_prometheus())
 }
 
 #[derive(Debug, PartialEq)]
@@ -88,10 +211,209 @@ pub fn calculate_resized_dimensions<T: ImageDimensions>(
     }
 }
 
+/// Codecs the transform endpoint can produce, replacing the old
+/// stringly-typed format allow-list with a single, self-describing type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum OutputFormat {
+    Avif,
+    Heic,
+    Jxl,
+    Qoi,
+    WebP,
+    Png,
+    Jpeg,
+}
+
+impl OutputFormat {
+    const ALL: [OutputFormat; 7] = [
+        OutputFormat::Avif,
+        OutputFormat::Heic,
+        OutputFormat::Jxl,
+        OutputFormat::Qoi,
+        OutputFormat::WebP,
+        OutputFormat::Png,
+        OutputFormat::Jpeg,
+    ];
+
+    fn from_ext(ext: &str) -> Option<Self> {
+        match ext.to_lowercase().as_str() {
+            "avif" => Some(Self::Avif),
+            "heic" => Some(Self::Heic),
+            "jxl" => Some(Self::Jxl),
+            "qoi" => Some(Self::Qoi),
+            "webp" => Some(Self::WebP),
+            "png" => Some(Self::Png),
+            "jpeg" | "jpg" => Some(Self::Jpeg),
+            _ => None,
+        }
+    }
+
+    fn as_ext(&self) -> &'static str {
+        match self {
+            Self::Avif => "avif",
+            Self::Heic => "heic",
+            Self::Jxl => "jxl",
+            Self::Qoi => "qoi",
+            Self::WebP => "webp",
+            Self::Png => "png",
+            Self::Jpeg => "jpeg",
+        }
+    }
+
+    fn content_type(&self) -> ContentType {
+        match self {
+            Self::Avif => ContentType::new("image", "avif"),
+            Self::Heic => ContentType::new("image", "heic"),
+            Self::Jxl => ContentType::new("image", "jxl"),
+            Self::Qoi => ContentType::new("image", "qoi"),
+            Self::WebP => ContentType::WEBP,
+            Self::Png => ContentType::PNG,
+            Self::Jpeg => ContentType::JPEG,
+        }
+    }
+
+    /// Whether this codec's encoder (as wired up here) preserves an alpha
+    /// channel; heic/jxl/jpeg are always encoded from RGB source data.
+    fn supports_alpha(&self) -> bool {
+        matches!(self, Self::Avif | Self::Qoi | Self::WebP | Self::Png)
+    }
+}
+
+#[get("/formats")]
+fn formats_handler() -> SerdeJsonValue {
+    json!({
+        "formats": OutputFormat::ALL.iter().map(OutputFormat::as_ext).collect::<Vec<_>>(),
+    })
+}
+
 #[derive(Deserialize, Clone)]
 struct ResizeRequest {
     tallestSide: u32,
+    /// An `OutputFormat` extension, or `"auto"` to pick the first format
+    /// from `accept` that is also in `AUTO_FORMAT_PREFERENCE` (falling back
+    /// to the first entry of `AUTO_FORMAT_PREFERENCE`).
     format: Option<String>,
+    /// Ordered format preference for `format: "auto"`, e.g. `["avif",
+    /// "webp", "jpeg"]` — analogous to an HTTP `Accept` header.
+    accept: Option<Vec<String>>,
+    quality: Option<u8>,
+    effort: Option<u8>,
+    lossless: Option<bool>,
+    watermark: Option<WatermarkRequest>,
+}
+
+/// Quality/speed/lossless knobs shared by the codecs that support them,
+/// resolved once per request so every `encode_*` function reads the same
+/// defaults.
+#[derive(Clone, Copy, Debug)]
+struct EncodeOptions {
+    quality: u8,
+    effort: u8,
+    lossless: bool,
+}
+
+impl EncodeOptions {
+    fn from_request(request: &ResizeRequest) -> Self {
+        Self {
+            quality: request.quality.unwrap_or(85).min(100),
+            effort: request.effort.unwrap_or(8),
+            lossless: request.lossless.unwrap_or(false),
+        }
+    }
+
+    /// Canonical string folded into the cache hash so differently encoded
+    /// variants (quality/effort/lossless) of the same source/size don't
+    /// collide.
+    fn cache_signature(&self) -> String {
+        format!(
+            "quality={};effort={};lossless={}",
+            self.quality, self.effort, self.lossless
+        )
+    }
+}
+
+/// The "modern" formats `format: "auto"` is allowed to pick, in priority
+/// order. Deliberately a subset of `OutputFormat::ALL` (no `heic`/`png`/
+/// `qoi`): those either aren't broadly supported by browsers or aren't
+/// worth auto-selecting over this list.
+const AUTO_FORMAT_PREFERENCE: [OutputFormat; 4] = [
+    OutputFormat::Avif,
+    OutputFormat::WebP,
+    OutputFormat::Jxl,
+    OutputFormat::Jpeg,
+];
+
+/// Resolves `format: "auto"` to a concrete `OutputFormat`: the first entry of
+/// `accept` that's also in `AUTO_FORMAT_PREFERENCE`, or — if `accept` is
+/// absent or none of it matches — the first entry of
+/// `AUTO_FORMAT_PREFERENCE` itself.
+fn resolve_auto_format(accept: Option<&[String]>) -> OutputFormat {
+    if let Some(accept) = accept {
+        for candidate in accept {
+            if let Some(format) =
+                OutputFormat::from_ext(candidate).filter(|f| AUTO_FORMAT_PREFERENCE.contains(f))
+            {
+                return format;
+            }
+        }
+    }
+    AUTO_FORMAT_PREFERENCE[0]
+}
+
+/// Optional watermark/copyright overlay baked into the resized output.
+/// Either `text` (rendered with the bundled bitmap font) or `image` (a
+/// filename resolved under `WATERMARK_DIR`, not an arbitrary filesystem
+/// path — see `resolve_watermark_path`) may be supplied; if both are set,
+/// `image` takes priority. `position` is one of `top-left`/`top-right`/
+/// `bottom-left`/`bottom-right` and defaults to `bottom-right`.
+#[derive(Deserialize, Clone, Debug)]
+struct WatermarkRequest {
+    text: Option<String>,
+    image: Option<String>,
+    position: Option<String>,
+    opacity: Option<f32>,
+    margin: Option<u32>,
+}
+
+impl WatermarkRequest {
+    fn position(&self) -> WatermarkPosition {
+        match self.position.as_deref() {
+            Some("top-left") => WatermarkPosition::TopLeft,
+            Some("top-right") => WatermarkPosition::TopRight,
+            Some("bottom-left") => WatermarkPosition::BottomLeft,
+            _ => WatermarkPosition::BottomRight,
+        }
+    }
+
+    fn opacity(&self) -> f32 {
+        self.opacity.unwrap_or(0.5).clamp(0.0, 1.0)
+    }
+
+    fn margin(&self) -> u32 {
+        self.margin.unwrap_or(16)
+    }
+
+    /// Canonical string folded into the cache hash so differently
+    /// watermarked variants of the same source/size don't collide.
+    fn cache_signature(&self) -> String {
+        format!(
+            "text={:?};image={:?};position={:?};opacity={};margin={}",
+            self.text,
+            self.image,
+            self.position,
+            self.opacity(),
+            self.margin(),
+        )
+    }
+}
+
+#[derive(Clone, Copy)]
+enum WatermarkPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
 }
 
 fn calculate_hash(input: &str) -> String {
@@ -101,8 +423,10 @@ fn calculate_hash(input: &str) -> String {
 }
 
 #[post("/<_..>", format = "json", data = "<data>")]
+#[tracing::instrument(name = "resize_handler", skip(data), fields(source))]
 async fn resize_handler(url: &Origin<'_>, data: Data<'_>) -> Result<SerdeJsonValue, CustomError> {
     let source = format!("{}", url).strip_prefix("/").unwrap().to_string();
+    tracing::Span::current().record("source", source.as_str());
 
     let request: ResizeRequest = serde_json::from_str(
         &String::from_utf8(
@@ -115,34 +439,61 @@ async fn resize_handler(url: &Origin<'_>, data: Data<'_>) -> Result<SerdeJsonVal
         .map_err(|_| CustomError::BadRequest)?,
     )?;
 
-    let format_str = request
+    let output_format = if request
         .format
-        .as_ref()
-        .map_or(String::new(), |s| s.to_lowercase());
-
-    if !["avif", "heic", "jxl", "qoi"].contains(&format_str.as_str()) {
-        return Err(CustomError::UnsupportedFormat);
-    }
+        .as_deref()
+        .is_some_and(|f| f.eq_ignore_ascii_case("auto"))
+    {
+        resolve_auto_format(request.accept.as_deref())
+    } else {
+        request
+            .format
+            .as_deref()
+            .and_then(OutputFormat::from_ext)
+            .ok_or(CustomError::UnsupportedFormat)?
+    };
+    let encode_options = EncodeOptions::from_request(&request);
 
     let hash_str = calculate_hash(&source);
-    let resized_filename = format!("{hash_str}_{}.{}", request.tallestSide, format_str.as_str());
+    let watermark_suffix = request
+        .watermark
+        .as_ref()
+        .map(|w| format!("_w{}", &calculate_hash(&w.cache_signature())[..8]))
+        .unwrap_or_default();
+    let encode_suffix = if request.quality.is_some() || request.effort.is_some() || request.lossless.is_some() {
+        format!("_e{}", &calculate_hash(&encode_options.cache_signature())[..8])
+    } else {
+        String::new()
+    };
+    let resized_filename = format!(
+        "{hash_str}_{}{watermark_suffix}{encode_suffix}.{}",
+        request.tallestSide,
+        output_format.as_ext()
+    );
     let images_dir = Path::new("images");
     fs::create_dir_all(images_dir).map_err(|_| CustomError::DirectoryCreationError)?;
     let resized_image_path = images_dir.join(&resized_filename);
+    let blurhash_path = images_dir.join(format!("{resized_filename}.blurhash"));
 
     if resized_image_path.exists() {
+        metrics().record_cache_hit();
+        let blurhash = fs::read_to_string(&blurhash_path).unwrap_or_default();
+        tracing::info!(hash = %hash_str, filename = %resized_filename, "already transformed, serving from cache");
         return Ok(json!({
             "status": "ALREADY_TRANSFORMED",
             "hash": &hash_str,
             "filename": resized_filename,
+            "blurhash": blurhash,
+            "format": output_format.as_ext(),
         }));
     }
+    metrics().record_cache_miss();
 
     let mut download_duration = 0.0;
     let downloaded_image_path = images_dir.join(&hash_str);
     let image_bytes: Vec<u8> = match fs::read(&downloaded_image_path) {
         Ok(bytes) => {
-            log::info!("CACHE HIT: Reading image {} from file.", &hash_str);
+            tracing::info!(hash = %hash_str, "cache hit: reading source image from file");
             if bytes.is_empty() {
                 return Err(CustomError::FileCorruptError);
             }
@@ -150,16 +501,24 @@ async fn resize_handler(url: &Origin<'_>, data: Data<'_>) -> Result<SerdeJsonVal
         }
         Err(e) => {
             if e.kind() == std::io::ErrorKind::NotFound {
-                log::info!("CACHE MISS: Downloading image for source: {}", &source);
+                tracing::info!(source = %source, "cache miss: downloading source image");
                 let download_start = Instant::now();
-                let downloaded_image_bytes = Client::new()
+                let response = Client::new()
                     .get(&source)
                     .send()
                     .await
-                    .map_err(|_| CustomError::DownloadError)?
+                    .map_err(|_| CustomError::DownloadError { upstream_status: None })?;
+
+                if !response.status().is_success() {
+                    return Err(CustomError::DownloadError {
+                        upstream_status: Some(response.status()),
+                    });
+                }
+
+                let downloaded_image_bytes = response
                     .bytes()
                     .await
-                    .map_err(|_| CustomError::DownloadError)?
+                    .map_err(|_| CustomError::DownloadError { upstream_status: None })?
                     .to_vec();
                 download_duration = download_start.elapsed().as_secs_f64() * 1000.0;
 
@@ -170,7 +529,7 @@ async fn resize_handler(url: &Origin<'_>, data: Data<'_>) -> Result<SerdeJsonVal
                 file.write_all(&downloaded_image_bytes)
                     .map_err(|_| CustomError::FileWriteError)?;
 
-                log::info!("CACHE WRITE: Saved image {} to file.", &hash_str);
+                tracing::info!(hash = %hash_str, "cache write: saved source image to file");
                 downloaded_image_bytes
             } else {
                 return Err(CustomError::FileReadError);
@@ -182,13 +541,14 @@ async fn resize_handler(url: &Origin<'_>, data: Data<'_>) -> Result<SerdeJsonVal
         return Err(CustomError::BadRequest);
     }
 
+    metrics().record_stage_duration("Downloading", download_duration);
     let resized_image_path_for_task = resized_image_path.clone();
 
-    let (encoded_data, mut metrics) = task::spawn_blocking(move || {
+    let (encoded_data, blurhash) = task::spawn_blocking(move || {
         process_image(
             image_bytes,
             request,
-            format_str,
+            output_format,
             resized_image_path_for_task,
         )
     })
@@ -196,14 +556,6 @@ async fn resize_handler(url: &Origin<'_>, data: Data<'_>) -> Result<SerdeJsonVal
     .map_err(|e| CustomError::ProcessingError(e.to_string()))?
     .map_err(|e| e)?;
 
-    metrics.insert(
-        0,
-        PerformanceMetrics {
-            step: "Downloading",
-            duration_ms: download_duration,
-        },
-    );
-
     let save_start = Instant::now();
     if !encoded_data.is_empty() {
         // Use the same create-then-write pattern for the transformed image.
@@ -212,37 +564,170 @@ async fn resize_handler(url: &Origin<'_>, data: Data<'_>) -> Result<SerdeJsonVal
         file.write_all(&encoded_data)
             .map_err(|_| CustomError::FileWriteError)?;
     }
+    fs::write(&blurhash_path, &blurhash).map_err(|_| CustomError::FileWriteError)?;
     let save_duration = save_start.elapsed().as_secs_f64() * 1000.0;
+    metrics().record_stage_duration("Saving", save_duration);
+    metrics().record_encode(output_format.as_ext());
 
-    metrics.push(PerformanceMetrics {
-        step: "Saving",
-        duration_ms: save_duration,
-    });
-
-    let table = Table::new(metrics).to_string();
-    log::info!("Processing complete for {}:\n{}", resized_filename, table);
+    tracing::info!(filename = %resized_filename, "processing complete");
 
     Ok(json!({
         "status": "TRANSFORMED",
         "hash": hash_str,
         "filename": resized_filename,
+        "blurhash": blurhash,
+        "format": output_format.as_ext(),
     }))
 }
 
+/// Parsed single-range `Range` request header, e.g. `bytes=0-499`.
+///
+/// Only a single byte range is supported, which covers the overwhelming
+/// majority of real-world clients (media players, browser `<video>`/`<img>`
+/// seeking, download managers).
+struct RangeHeader(Option<(u64, Option<u64>)>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RangeHeader {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let range = request
+            .headers()
+            .get_one("Range")
+            .and_then(parse_range_header);
+        request::Outcome::Success(RangeHeader(range))
+    }
+}
+
+fn parse_range_header(header: &str) -> Option<(u64, Option<u64>)> {
+    let spec = header.strip_prefix("bytes=")?;
+    let (start, end) = spec.split_once('-')?;
+    let start: u64 = start.parse().ok()?;
+    let end = if end.is_empty() {
+        None
+    } else {
+        Some(end.parse().ok()?)
+    };
+    Some((start, end))
+}
+
+fn content_type_for_format(format_str: &str) -> ContentType {
+    OutputFormat::from_ext(format_str)
+        .map(|format| format.content_type())
+        .unwrap_or(ContentType::Binary)
+}
+
+/// Streams a cached file from `images/`, honouring a single-range `Range`
+/// request for partial content and returning 404 when it doesn't exist.
+async fn serve_blob(
+    path: PathBuf,
+    content_type: ContentType,
+    range: RangeHeader,
+) -> Result<Response<'static>, Status> {
+    let bytes = task::spawn_blocking(move || fs::read(&path))
+        .await
+        .map_err(|_| Status::InternalServerError)?
+        .map_err(|_| Status::NotFound)?;
+
+    let len = bytes.len() as u64;
+
+    let mut response = Response::build();
+    response.header(content_type);
+    response.raw_header("Accept-Ranges", "bytes");
+
+    match range.0 {
+        Some((start, end)) if start < len => {
+            let end = end.unwrap_or(len - 1).min(len - 1);
+            if start > end {
+                return Err(Status::RangeNotSatisfiable);
+            }
+            let slice = bytes[start as usize..=end as usize].to_vec();
+            response.status(Status::PartialContent);
+            response.raw_header("Content-Range", format!("bytes {start}-{end}/{len}"));
+            response.sized_body(slice.len(), Cursor::new(slice));
+        }
+        Some(_) => {
+            response.status(Status::RangeNotSatisfiable);
+            response.raw_header("Content-Range", format!("bytes */{len}"));
+        }
+        None => {
+            response.status(Status::Ok);
+            response.sized_body(bytes.len(), Cursor::new(bytes));
+        }
+    }
+
+    Ok(response.finalize())
+}
+
+/// Generic retrieval by stored filename — this is the canonical way to fetch
+/// any cached blob, including watermarked/quality-tuned variants (which carry
+/// a `_w…`/`_e…` suffix that `blob_variant_handler` doesn't reconstruct): pass
+/// the `filename` from a `resize_handler` response straight through as
+/// `<hash>`. The content type is inferred from its extension, falling back to
+/// `ContentType::Binary` for extension-less source blobs.
+#[get("/blob/<hash>")]
+async fn blob_handler(hash: &str, range: RangeHeader) -> Result<Response<'static>, Status> {
+    let path = Path::new("images").join(hash);
+    let content_type = hash
+        .rsplit_once('.')
+        .map(|(_, ext)| content_type_for_format(ext))
+        .unwrap_or(ContentType::Binary);
+    serve_blob(path, content_type, range).await
+}
+
+/// Convenience shorthand for the default (no watermark, default encode
+/// options) variant of a resize; reconstructs `"{hash}_{tallest_side}.
+/// {format}"` directly from the path segments. Watermarked or quality-tuned
+/// variants carry an extra `_w…`/`_e…` suffix this route doesn't know about —
+/// fetch those through `blob_handler` using the `filename` returned by
+/// `resize_handler` instead.
+#[get("/blob/<hash>/<tallest_side>/<format>")]
+async fn blob_variant_handler(
+    hash: &str,
+    tallest_side: u32,
+    format: &str,
+    range: RangeHeader,
+) -> Result<Response<'static>, Status> {
+    let format_str = format.to_lowercase();
+    let filename = format!("{hash}_{tallest_side}.{format_str}");
+    let path = Path::new("images").join(filename);
+    serve_blob(path, content_type_for_format(&format_str), range).await
+}
+
+#[tracing::instrument(skip(image_bytes, request), fields(format = output_format.as_ext(), tallest_side = request.tallestSide))]
 fn process_image(
     image_bytes: Vec<u8>,
     request: ResizeRequest,
-    format_str: String,
+    output_format: OutputFormat,
     resized_image_path: PathBuf,
-) -> Result<(Vec<u8>, Vec<PerformanceMetrics>), CustomError> {
-    let mut metrics = Vec::new();
-
+) -> Result<(Vec<u8>, String), CustomError> {
     let load_start = Instant::now();
-    let img = image::load_from_memory(&image_bytes).map_err(|_| CustomError::ImageDecodeError)?;
-    metrics.push(PerformanceMetrics {
-        step: "Decoding",
-        duration_ms: load_start.elapsed().as_secs_f64() * 1000.0,
-    });
+    let img = {
+        #[cfg(feature = "video")]
+        {
+            // Animated GIFs (and anything else the `image` crate can
+            // partially decode) would otherwise succeed here and silently
+            // thumbnail raw frame 0, skipping the seek this request asks
+            // for. Route genuine video/GIF sources to `extract_frame` up
+            // front based on their magic bytes, not just on decode failure.
+            if looks_like_video_or_gif(&image_bytes) {
+                extract_frame(&image_bytes)?
+            } else {
+                match image::load_from_memory(&image_bytes) {
+                    Ok(img) => img,
+                    Err(_) => extract_frame(&image_bytes)?,
+                }
+            }
+        }
+        #[cfg(not(feature = "video"))]
+        {
+            image::load_from_memory(&image_bytes).map_err(|_| CustomError::ImageDecodeError)?
+        }
+    };
+    let decode_duration = load_start.elapsed().as_secs_f64() * 1000.0;
+    metrics().record_stage_duration("Decoding", decode_duration);
+    tracing::debug!(duration_ms = decode_duration, "decoded source image");
 
     let resize_start = Instant::now();
     let resized_dims = calculate_resized_dimensions(&img, request.tallestSide);
@@ -257,32 +742,245 @@ fn process_image(
                 .use_alpha(false),
         )
         .map_err(|_| CustomError::ResizeError)?;
-    metrics.push(PerformanceMetrics {
-        step: "Resizing",
-        duration_ms: resize_start.elapsed().as_secs_f64() * 1000.0,
-    });
+    let resize_duration = resize_start.elapsed().as_secs_f64() * 1000.0;
+    metrics().record_stage_duration("Resizing", resize_duration);
+    tracing::debug!(duration_ms = resize_duration, "resized image");
 
+    if let Some(watermark) = &request.watermark {
+        let watermark_start = Instant::now();
+        apply_watermark(&mut dst_image, watermark)?;
+        let watermark_duration = watermark_start.elapsed().as_secs_f64() * 1000.0;
+        metrics().record_stage_duration("Watermarking", watermark_duration);
+        tracing::debug!(duration_ms = watermark_duration, "applied watermark");
+    }
+
+    let blurhash_start = Instant::now();
+    let blurhash = encode_blurhash(&dst_image, BLURHASH_COMPONENTS_X, BLURHASH_COMPONENTS_Y);
+    let blurhash_duration = blurhash_start.elapsed().as_secs_f64() * 1000.0;
+    metrics().record_stage_duration("Blurhashing", blurhash_duration);
+    tracing::debug!(duration_ms = blurhash_duration, "computed blurhash");
+
+    let encode_options = EncodeOptions::from_request(&request);
     let encode_start = Instant::now();
-    let encoded_data = match format_str.as_str() {
-        "avif" => encode_avif(&dst_image),
-        "heic" => {
-            encode_heic(&resized_image_path, &dst_image)?;
-            Ok(Vec::new())
+    let encoded_data = match encode(
+        output_format,
+        &dst_image,
+        &resized_image_path,
+        encode_options,
+    )? {
+        Encoded::InMemory(bytes) => bytes,
+        Encoded::WrittenToFile => Vec::new(),
+    };
+    let encode_duration = encode_start.elapsed().as_secs_f64() * 1000.0;
+    metrics().record_stage_duration("Encoding", encode_duration);
+    tracing::debug!(duration_ms = encode_duration, "encoded image");
+
+    Ok((encoded_data, blurhash))
+}
+
+/// Default BlurHash grid: 4 components across, 3 down. A sensible balance
+/// between placeholder fidelity and string length for typical photos.
+const BLURHASH_COMPONENTS_X: u32 = 4;
+const BLURHASH_COMPONENTS_Y: u32 = 3;
+
+const BLURHASH_CHARACTERS: &[u8] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+fn encode_base83(mut value: u32, length: usize) -> String {
+    let mut result = vec![0u8; length];
+    for slot in result.iter_mut().rev() {
+        *slot = BLURHASH_CHARACTERS[(value % 83) as usize];
+        value /= 83;
+    }
+    String::from_utf8(result).unwrap()
+}
+
+fn srgb_to_linear(value: u8) -> f64 {
+    let v = value as f64 / 255.0;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f64) -> u8 {
+    let v = value.clamp(0.0, 1.0);
+    let srgb = if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    (srgb * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+fn sign_pow(value: f64, exponent: f64) -> f64 {
+    value.signum() * value.abs().powf(exponent)
+}
+
+/// Encodes a BlurHash placeholder string for `image`, using a
+/// `components_x` x `components_y` grid of DCT components, following the
+/// reference BlurHash algorithm (https://github.com/woltapp/blurhash).
+fn encode_blurhash(image: &DynamicImage, components_x: u32, components_y: u32) -> String {
+    let rgba = image.to_rgba8();
+    let (width, height) = (rgba.width().max(1) as usize, rgba.height().max(1) as usize);
+
+    let mut factors = Vec::with_capacity((components_x * components_y) as usize);
+    for j in 0..components_y {
+        for i in 0..components_x {
+            let normalisation = if i == 0 && j == 0 { 1.0 } else { 2.0 };
+            let (mut r, mut g, mut b) = (0.0, 0.0, 0.0);
+            for y in 0..height {
+                for x in 0..width {
+                    let basis = (std::f64::consts::PI * i as f64 * x as f64 / width as f64).cos()
+                        * (std::f64::consts::PI * j as f64 * y as f64 / height as f64).cos();
+                    let pixel = rgba.get_pixel(x as u32, y as u32);
+                    r += basis * srgb_to_linear(pixel[0]);
+                    g += basis * srgb_to_linear(pixel[1]);
+                    b += basis * srgb_to_linear(pixel[2]);
+                }
+            }
+            let scale = normalisation / (width * height) as f64;
+            factors.push((r * scale, g * scale, b * scale));
         }
-        "jxl" => encode_jxl(&dst_image),
-        "qoi" => encode_qoi(&dst_image),
-        _ => unreachable!(),
-    }?;
-    metrics.push(PerformanceMetrics {
-        step: "Encoding",
-        duration_ms: encode_start.elapsed().as_secs_f64() * 1000.0,
-    });
-
-    Ok((encoded_data, metrics))
-}
-fn encode_avif(dst_image: &DynamicImage) -> Result<Vec<u8>, CustomError> {
+    }
+
+    let dc = factors[0];
+    let ac = &factors[1..];
+
+    let mut hash = encode_base83((components_x - 1) + (components_y - 1) * 9, 1);
+
+    let max_ac = ac
+        .iter()
+        .flat_map(|&(r, g, b)| [r.abs(), g.abs(), b.abs()])
+        .fold(0.0_f64, f64::max);
+    let quantised_max_ac = if ac.is_empty() {
+        0
+    } else {
+        (max_ac * 166.0 - 0.5).floor().clamp(0.0, 82.0) as u32
+    };
+    hash.push_str(&encode_base83(quantised_max_ac, 1));
+
+    let dc_value = ((linear_to_srgb(dc.0) as u32) << 16)
+        | ((linear_to_srgb(dc.1) as u32) << 8)
+        | linear_to_srgb(dc.2) as u32;
+    hash.push_str(&encode_base83(dc_value, 4));
+
+    let actual_max_ac = (quantised_max_ac as f64 + 1.0) / 166.0;
+    for &(r, g, b) in ac {
+        let quantise = |value: f64| -> u32 {
+            (sign_pow(value / actual_max_ac, 0.5) * 9.0 + 9.5)
+                .floor()
+                .clamp(0.0, 18.0) as u32
+        };
+        let ac_value = quantise(r) * 19 * 19 + quantise(g) * 19 + quantise(b);
+        hash.push_str(&encode_base83(ac_value, 2));
+    }
+
+    hash
+}
+
+#[cfg(test)]
+mod blurhash_tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn solid_image(width: u32, height: u32, rgb: [u8; 3]) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+            width,
+            height,
+            Rgba([rgb[0], rgb[1], rgb[2], 255]),
+        ))
+    }
+
+    #[test]
+    fn base83_encodes_known_values() {
+        assert_eq!(encode_base83(0, 1), "0");
+        assert_eq!(encode_base83(82, 1), "~");
+        assert_eq!(encode_base83(0, 4), "0000");
+        assert_eq!(encode_base83(16777215, 4), "TSUA");
+        assert_eq!(encode_base83(3429, 2), "fQ");
+    }
+
+    #[test]
+    fn srgb_linear_round_trip_at_extremes() {
+        assert_eq!(srgb_to_linear(0), 0.0);
+        assert_eq!(srgb_to_linear(255), 1.0);
+        assert_eq!(linear_to_srgb(0.0), 0);
+        assert_eq!(linear_to_srgb(1.0), 255);
+        assert_eq!(linear_to_srgb(srgb_to_linear(0)), 0);
+        assert_eq!(linear_to_srgb(srgb_to_linear(255)), 255);
+    }
+
+    #[test]
+    fn encodes_solid_black_as_flat_hash() {
+        let image = solid_image(1, 1, [0, 0, 0]);
+        assert_eq!(encode_blurhash(&image, 1, 1), "000000");
+    }
+
+    #[test]
+    fn encodes_solid_white_as_flat_hash() {
+        let image = solid_image(1, 1, [255, 255, 255]);
+        assert_eq!(encode_blurhash(&image, 1, 1), "00TSUA");
+    }
+
+    #[test]
+    fn encodes_two_tone_row_with_one_ac_component() {
+        let mut image = RgbaImage::new(2, 1);
+        image.put_pixel(0, 0, Rgba([0, 0, 0, 255]));
+        image.put_pixel(1, 0, Rgba([255, 255, 255, 255]));
+        let hash = encode_blurhash(&DynamicImage::ImageRgba8(image), 2, 1);
+        assert_eq!(hash, "10Lqe9fQ");
+    }
+}
+
+/// Result of an `encode()` call. Most codecs return their bytes in memory,
+/// but `heic` only exposes a file-based writer (`encode_heic`), so it
+/// writes straight to `resized_image_path` and reports back via this marker
+/// instead of faking an empty in-memory buffer.
+enum Encoded {
+    InMemory(Vec<u8>),
+    WrittenToFile,
+}
+
+/// Single dispatch point for every supported codec; adding a new one is one
+/// `OutputFormat` variant plus one `encode_*` function and match arm here.
+fn encode(
+    format: OutputFormat,
+    dst_image: &DynamicImage,
+    resized_image_path: &Path,
+    options: EncodeOptions,
+) -> Result<Encoded, CustomError> {
+    // Formats without alpha support ignore the channel anyway, but flatten
+    // up front so every codec downstream sees the same RGB source data.
+    let flattened;
+    let dst_image = if !format.supports_alpha() && dst_image.color().has_alpha() {
+        flattened = DynamicImage::ImageRgb8(dst_image.to_rgb8());
+        &flattened
+    } else {
+        dst_image
+    };
+
+    match format {
+        OutputFormat::Avif => encode_avif(dst_image, options).map(Encoded::InMemory),
+        OutputFormat::Heic => {
+            encode_heic(resized_image_path, dst_image, options)?;
+            Ok(Encoded::WrittenToFile)
+        }
+        OutputFormat::Jxl => encode_jxl(dst_image, options).map(Encoded::InMemory),
+        OutputFormat::Qoi => encode_qoi(dst_image).map(Encoded::InMemory),
+        OutputFormat::WebP => encode_webp(dst_image).map(Encoded::InMemory),
+        OutputFormat::Png => encode_png(dst_image).map(Encoded::InMemory),
+        OutputFormat::Jpeg => encode_jpeg(dst_image, options).map(Encoded::InMemory),
+    }
+}
+
+fn encode_avif(dst_image: &DynamicImage, options: EncodeOptions) -> Result<Vec<u8>, CustomError> {
     let mut buf = Cursor::new(Vec::new());
-    AvifEncoder::new_with_speed_quality(&mut buf, 8, 85)
+    let quality = if options.lossless { 100 } else { options.quality };
+    // `AvifEncoder` speed runs 1 (slowest) to 10 (fastest), the inverse of `effort`.
+    let speed = 10 - options.effort.min(9);
+    AvifEncoder::new_with_speed_quality(&mut buf, speed, quality)
         .write_image(
             dst_image.as_bytes(),
             dst_image.width(),
@@ -293,7 +991,11 @@ fn encode_avif(dst_image: &DynamicImage) -> Result<Vec<u8>, CustomError> {
     Ok(buf.into_inner())
 }
 
-fn encode_heic(output_file: &Path, dst_image: &DynamicImage) -> Result<(), CustomError> {
+fn encode_heic(
+    output_file: &Path,
+    dst_image: &DynamicImage,
+    options: EncodeOptions,
+) -> Result<(), CustomError> {
     let width = dst_image.width();
     let height = dst_image.height();
     let rgb_buf = dst_image.to_rgb8().into_raw();
@@ -326,8 +1028,13 @@ fn encode_heic(output_file: &Path, dst_image: &DynamicImage) -> Result<(), Custo
     let mut encoder = lib_heif
         .encoder_for_format(CompressionFormat::Hevc)
         .map_err(|_| CustomError::ImageEncodeError)?;
+    let quality = if options.lossless {
+        EncoderQuality::Lossless
+    } else {
+        EncoderQuality::Lossy(options.quality)
+    };
     encoder
-        .set_quality(EncoderQuality::Lossy(85))
+        .set_quality(quality)
         .map_err(|_| CustomError::ImageEncodeError)?;
     context
         .encode_image(&heic_image, &mut encoder, None)
@@ -342,15 +1049,37 @@ fn encode_heic(output_file: &Path, dst_image: &DynamicImage) -> Result<(), Custo
     Ok(())
 }
 
-fn encode_jxl(dst_image: &DynamicImage) -> Result<Vec<u8>, CustomError> {
+/// Maps the caller-facing 0-10 `effort` scale (higher = slower, better
+/// compression) onto `jpegxl_rs`'s named speed tiers.
+fn jxl_speed_for_effort(effort: u8) -> EncoderSpeed {
+    match effort {
+        0..=1 => EncoderSpeed::Lightning,
+        2..=3 => EncoderSpeed::Thunder,
+        4..=5 => EncoderSpeed::Falcon,
+        6 => EncoderSpeed::Cheetah,
+        7 => EncoderSpeed::Hare,
+        8 => EncoderSpeed::Wombat,
+        9 => EncoderSpeed::Squirrel,
+        _ => EncoderSpeed::Tortoise,
+    }
+}
+
+/// Maps the caller-facing 0-100 "higher is better" `quality` onto
+/// `jpegxl_rs`'s butteraugli *distance* scale, where 0 is lossless and ~15 is
+/// the lowest-quality end of the visual range.
+fn jxl_distance_for_quality(quality: u8) -> f32 {
+    (100 - quality.min(100)) as f32 / 100.0 * 15.0
+}
+
+fn encode_jxl(dst_image: &DynamicImage, options: EncodeOptions) -> Result<Vec<u8>, CustomError> {
     let width = dst_image.width();
     let height = dst_image.height();
     let rgb_buf = dst_image.to_rgb8().into_raw();
 
     let mut encoder = encoder_builder()
-        .lossless(false)
-        .speed(EncoderSpeed::Falcon)
-        .quality(1.0)
+        .lossless(options.lossless)
+        .speed(jxl_speed_for_effort(options.effort))
+        .quality(jxl_distance_for_quality(options.quality))
         .build()
         .map_err(|_| CustomError::ImageEncodeError)?;
 
@@ -380,10 +1109,355 @@ fn encode_qoi(dst_image: &DynamicImage) -> Result<Vec<u8>, CustomError> {
         .map_err(|_| CustomError::ImageEncodeError)
 }
 
+fn encode_webp(dst_image: &DynamicImage) -> Result<Vec<u8>, CustomError> {
+    let mut buf = Cursor::new(Vec::new());
+    dst_image
+        .write_to(&mut buf, image::ImageFormat::WebP)
+        .map_err(|_| CustomError::ImageEncodeError)?;
+    Ok(buf.into_inner())
+}
+
+fn encode_png(dst_image: &DynamicImage) -> Result<Vec<u8>, CustomError> {
+    let mut buf = Cursor::new(Vec::new());
+    dst_image
+        .write_to(&mut buf, image::ImageFormat::Png)
+        .map_err(|_| CustomError::ImageEncodeError)?;
+    Ok(buf.into_inner())
+}
+
+fn encode_jpeg(dst_image: &DynamicImage, options: EncodeOptions) -> Result<Vec<u8>, CustomError> {
+    let mut buf = Cursor::new(Vec::new());
+    JpegEncoder::new_with_quality(&mut buf, options.quality)
+        .write_image(
+            dst_image.to_rgb8().as_raw(),
+            dst_image.width(),
+            dst_image.height(),
+            ExtendedColorType::Rgb8,
+        )
+        .map_err(|_| CustomError::ImageEncodeError)?;
+    Ok(buf.into_inner())
+}
+
+/// Base directory watermark overlay images are served from; `watermark.image`
+/// is resolved relative to this by `resolve_watermark_path` and is never
+/// treated as an arbitrary filesystem path.
+const WATERMARK_DIR: &str = "watermarks";
+
+/// Resolves a client-supplied `watermark.image` filename to a path under
+/// `WATERMARK_DIR`, rejecting absolute paths and `..` components so the
+/// request can't be used to read arbitrary files off the server.
+fn resolve_watermark_path(requested: &str) -> Result<PathBuf, CustomError> {
+    let requested_path = Path::new(requested);
+    if requested_path.is_absolute()
+        || requested_path
+            .components()
+            .any(|component| matches!(component, std::path::Component::ParentDir))
+    {
+        return Err(CustomError::BadRequest);
+    }
+    Ok(Path::new(WATERMARK_DIR).join(requested_path))
+}
+
+/// Composites the requested watermark onto `dst_image` in place, either a
+/// rendered text overlay or a scaled-down PNG, alpha-blended at the chosen
+/// corner.
+fn apply_watermark(
+    dst_image: &mut DynamicImage,
+    watermark: &WatermarkRequest,
+) -> Result<(), CustomError> {
+    let overlay = if let Some(image_path) = &watermark.image {
+        let image_path = resolve_watermark_path(image_path)?;
+        let source = image::open(&image_path).map_err(|_| CustomError::ImageDecodeError)?;
+        let max_width = ((dst_image.width() as f32) * 0.25).round().max(1.0) as u32;
+        let scale = (max_width as f32 / source.width().max(1) as f32).min(1.0);
+        let target_width = ((source.width() as f32) * scale).round().max(1.0) as u32;
+        let target_height = ((source.height() as f32) * scale).round().max(1.0) as u32;
+        source
+            .resize(
+                target_width,
+                target_height,
+                image::imageops::FilterType::CatmullRom,
+            )
+            .to_rgba8()
+    } else if let Some(text) = &watermark.text {
+        render_text_layer(text)
+    } else {
+        return Ok(());
+    };
+
+    let (x, y) = watermark_anchor(dst_image, &overlay, watermark);
+    blend_overlay(dst_image, &overlay, x, y, watermark.opacity());
+    Ok(())
+}
+
+fn watermark_anchor(
+    dst_image: &DynamicImage,
+    overlay: &image::RgbaImage,
+    watermark: &WatermarkRequest,
+) -> (i64, i64) {
+    let margin = watermark.margin() as i64;
+    let (dst_width, dst_height) = (dst_image.width() as i64, dst_image.height() as i64);
+    let (overlay_width, overlay_height) = (overlay.width() as i64, overlay.height() as i64);
+
+    match watermark.position() {
+        WatermarkPosition::TopLeft => (margin, margin),
+        WatermarkPosition::TopRight => (dst_width - overlay_width - margin, margin),
+        WatermarkPosition::BottomLeft => (margin, dst_height - overlay_height - margin),
+        WatermarkPosition::BottomRight => (
+            dst_width - overlay_width - margin,
+            dst_height - overlay_height - margin,
+        ),
+    }
+}
+
+fn blend_overlay(dst_image: &mut DynamicImage, overlay: &image::RgbaImage, x: i64, y: i64, opacity: f32) {
+    let mut dst_rgba = dst_image.to_rgba8();
+    let (dst_width, dst_height) = (dst_rgba.width() as i64, dst_rgba.height() as i64);
+
+    for (overlay_x, overlay_y, overlay_pixel) in overlay.enumerate_pixels() {
+        let dst_x = x + overlay_x as i64;
+        let dst_y = y + overlay_y as i64;
+        if dst_x < 0 || dst_y < 0 || dst_x >= dst_width || dst_y >= dst_height {
+            continue;
+        }
+
+        let alpha = (overlay_pixel[3] as f32 / 255.0) * opacity;
+        if alpha <= 0.0 {
+            continue;
+        }
+
+        let dst_pixel = dst_rgba.get_pixel_mut(dst_x as u32, dst_y as u32);
+        for channel in 0..3 {
+            let blended =
+                overlay_pixel[channel] as f32 * alpha + dst_pixel[channel] as f32 * (1.0 - alpha);
+            dst_pixel[channel] = blended.round().clamp(0.0, 255.0) as u8;
+        }
+    }
+
+    *dst_image = DynamicImage::ImageRgba8(dst_rgba);
+}
+
+/// Minimal bundled 5x7 bitmap font covering letters, digits, and a handful
+/// of punctuation marks common in watermark text (e.g. "(C) 2026 Me").
+/// Characters outside this set render as a blank cell.
+fn glyph_bitmap(ch: char) -> [u8; 7] {
+    match ch {
+        'A' => [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'B' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110],
+        'C' => [0b01111, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b01111],
+        'D' => [0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110],
+        'E' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111],
+        'F' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000],
+        'G' => [0b01111, 0b10000, 0b10000, 0b10011, 0b10001, 0b10001, 0b01111],
+        'H' => [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001],
+        'I' => [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        'J' => [0b00001, 0b00001, 0b00001, 0b00001, 0b10001, 0b10001, 0b01110],
+        'K' => [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001],
+        'L' => [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111],
+        'M' => [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001],
+        'N' => [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001],
+        'O' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'P' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000],
+        'Q' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101],
+        'R' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001],
+        'S' => [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110],
+        'T' => [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100],
+        'U' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'V' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+        'W' => [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b11011, 0b10001],
+        'X' => [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001],
+        'Y' => [0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100, 0b00100],
+        'Z' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111],
+        '0' => [0b01110, 0b10011, 0b10101, 0b10101, 0b10101, 0b11001, 0b01110],
+        '1' => [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        '2' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+        '3' => [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+        '4' => [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+        '5' => [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+        '6' => [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+        '7' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+        '8' => [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+        '9' => [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+        '.' => [0, 0, 0, 0, 0, 0b01100, 0b01100],
+        ',' => [0, 0, 0, 0, 0, 0b01100, 0b01000],
+        '-' => [0, 0, 0, 0b11111, 0, 0, 0],
+        ':' => [0, 0b01100, 0b01100, 0, 0b01100, 0b01100, 0],
+        '!' => [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0, 0b00100],
+        '?' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0, 0b00100],
+        '\'' => [0b01000, 0b01000, 0, 0, 0, 0, 0],
+        '(' => [0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010],
+        ')' => [0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000],
+        '&' => [0b01100, 0b10010, 0b10100, 0b01000, 0b10101, 0b10010, 0b01101],
+        _ => [0, 0, 0, 0, 0, 0, 0],
+    }
+}
+
+/// Upper bound on rendered watermark text length; the layer width is
+/// proportional to character count, so this caps the allocation at a small,
+/// fixed multiple of a single glyph cell rather than letting an arbitrarily
+/// long `text` value drive it.
+const MAX_WATERMARK_TEXT_CHARS: usize = 256;
+
+/// Renders `text` (uppercased, since the bundled font only covers that
+/// range) into a standalone RGBA layer of opaque white glyph pixels on a
+/// transparent background, ready to be alpha-blended onto an image.
+fn render_text_layer(text: &str) -> image::RgbaImage {
+    const GLYPH_WIDTH: u32 = 5;
+    const GLYPH_HEIGHT: u32 = 7;
+    const SCALE: u32 = 3;
+    const SPACING: u32 = 1;
+
+    let mut characters: Vec<char> = text.to_uppercase().chars().collect();
+    characters.truncate(MAX_WATERMARK_TEXT_CHARS);
+    let cell_width = (GLYPH_WIDTH + SPACING) * SCALE;
+    let width = (cell_width * characters.len() as u32).max(1);
+    let height = GLYPH_HEIGHT * SCALE;
+
+    let mut layer = image::RgbaImage::new(width, height);
+    for (index, ch) in characters.iter().enumerate() {
+        let bitmap = glyph_bitmap(*ch);
+        let origin_x = index as u32 * cell_width;
+        for (row, bits) in bitmap.iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - col)) == 0 {
+                    continue;
+                }
+                for scale_y in 0..SCALE {
+                    for scale_x in 0..SCALE {
+                        let x = origin_x + col * SCALE + scale_x;
+                        let y = row as u32 * SCALE + scale_y;
+                        layer.put_pixel(x, y, image::Rgba([255, 255, 255, 255]));
+                    }
+                }
+            }
+        }
+    }
+    layer
+}
+
+/// Sniffs the leading bytes for a GIF or common video container signature
+/// (ISOBMFF/mp4/mov, WebM/Matroska) so those sources route straight to
+/// `extract_frame`'s seek-based thumbnailing instead of being decoded as a
+/// static image by `image::load_from_memory` (which happily decodes an
+/// animated GIF's first frame on its own).
+#[cfg(feature = "video")]
+fn looks_like_video_or_gif(bytes: &[u8]) -> bool {
+    if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        return true;
+    }
+    if bytes.len() >= 12 && &bytes[4..8] == b"ftyp" {
+        // AVIF/HEIF still images share the ISOBMFF `ftyp` box; leave those
+        // to the still-image decoder.
+        let brand = &bytes[8..12];
+        return !(brand == b"avif" || brand == b"heic" || brand == b"heix" || brand == b"mif1");
+    }
+    if bytes.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        return true;
+    }
+    false
+}
+
+/// Decodes a still thumbnail from an animated GIF or video (mp4/webm)
+/// source, so the same resize/encode pipeline can serve poster frames.
+/// Only built when the `video` feature is enabled, keeping the ffmpeg
+/// system dependency optional.
+#[cfg(feature = "video")]
+fn extract_frame(bytes: &[u8]) -> Result<DynamicImage, CustomError> {
+    use ffmpeg_next as ffmpeg;
+
+    // ffmpeg-next only opens inputs by path, so stage the bytes in a temp file.
+    // Keyed on the content hash (not just the length) so two different
+    // but equal-length videos decoded concurrently in separate
+    // `spawn_blocking` tasks never clobber each other's staging file.
+    let mut hasher = Hasher::new();
+    hasher.update(bytes);
+    let temp_path =
+        std::env::temp_dir().join(format!("fih_video_{}.tmp", hasher.finalize().to_hex()));
+    fs::write(&temp_path, bytes).map_err(|_| CustomError::FileWriteError)?;
+
+    let result = (|| -> Result<DynamicImage, CustomError> {
+        ffmpeg::init().map_err(|_| CustomError::VideoDecodeError)?;
+
+        let mut input =
+            ffmpeg::format::input(&temp_path).map_err(|_| CustomError::VideoDecodeError)?;
+        let stream = input
+            .streams()
+            .best(ffmpeg::media::Type::Video)
+            .ok_or(CustomError::VideoDecodeError)?;
+        let stream_index = stream.index();
+
+        let duration_secs = input.duration() as f64 / f64::from(ffmpeg::ffi::AV_TIME_BASE);
+        let timestamp_secs = if duration_secs > 10.0 {
+            duration_secs * 0.1
+        } else {
+            duration_secs.min(1.0)
+        };
+
+        let context = ffmpeg::codec::context::Context::from_parameters(stream.parameters())
+            .map_err(|_| CustomError::VideoDecodeError)?;
+        let mut decoder = context
+            .decoder()
+            .video()
+            .map_err(|_| CustomError::VideoDecodeError)?;
+
+        let seek_target = (timestamp_secs * f64::from(ffmpeg::ffi::AV_TIME_BASE)) as i64;
+        input
+            .seek(seek_target, ..seek_target)
+            .map_err(|_| CustomError::VideoDecodeError)?;
+
+        let mut scaler = ffmpeg::software::scaling::Context::get(
+            decoder.format(),
+            decoder.width(),
+            decoder.height(),
+            ffmpeg::format::Pixel::RGB24,
+            decoder.width(),
+            decoder.height(),
+            ffmpeg::software::scaling::Flags::BILINEAR,
+        )
+        .map_err(|_| CustomError::VideoDecodeError)?;
+
+        let mut decoded_frame = ffmpeg::frame::Video::empty();
+        for (packet_stream, packet) in input.packets() {
+            if packet_stream.index() != stream_index {
+                continue;
+            }
+            decoder
+                .send_packet(&packet)
+                .map_err(|_| CustomError::VideoDecodeError)?;
+            if decoder.receive_frame(&mut decoded_frame).is_ok() {
+                let mut rgb_frame = ffmpeg::frame::Video::empty();
+                scaler
+                    .run(&decoded_frame, &mut rgb_frame)
+                    .map_err(|_| CustomError::VideoDecodeError)?;
+
+                let width = rgb_frame.width();
+                let height = rgb_frame.height();
+                let stride = rgb_frame.stride(0);
+                let data = rgb_frame.data(0);
+
+                let mut buffer = Vec::with_capacity((width * height * 3) as usize);
+                for row in 0..height as usize {
+                    let start = row * stride;
+                    buffer.extend_from_slice(&data[start..start + width as usize * 3]);
+                }
+
+                let rgb_image = image::RgbImage::from_raw(width, height, buffer)
+                    .ok_or(CustomError::VideoDecodeError)?;
+                return Ok(DynamicImage::ImageRgb8(rgb_image));
+            }
+        }
+
+        Err(CustomError::VideoDecodeError)
+    })();
+
+    let _ = fs::remove_file(&temp_path);
+    result
+}
+
 #[derive(Debug)]
 enum CustomError {
     UnsupportedFormat,
-    DownloadError,
+    DownloadError { upstream_status: Option<reqwest::StatusCode> },
     ImageDecodeError,
     ResizeError,
     FileCreationError,
@@ -395,6 +1469,38 @@ enum CustomError {
     BadRequest,
     ProcessingError(String),
     JsonDeserializeError(String),
+    #[cfg(feature = "video")]
+    VideoDecodeError,
+}
+
+impl CustomError {
+    /// Maps this error to the HTTP status a caller/proxy should see, so
+    /// upstream failures, bad input, and internal crashes are distinguishable.
+    fn status(&self) -> Status {
+        match self {
+            CustomError::BadRequest
+            | CustomError::UnsupportedFormat
+            | CustomError::JsonDeserializeError(_) => Status::BadRequest,
+            CustomError::DownloadError {
+                upstream_status: Some(_),
+            } => Status::BadGateway,
+            CustomError::DownloadError {
+                upstream_status: None,
+            } => Status::GatewayTimeout,
+            CustomError::FileCorruptError | CustomError::ImageDecodeError => {
+                Status::UnprocessableEntity
+            }
+            #[cfg(feature = "video")]
+            CustomError::VideoDecodeError => Status::UnprocessableEntity,
+            CustomError::FileCreationError
+            | CustomError::FileWriteError
+            | CustomError::FileReadError
+            | CustomError::DirectoryCreationError
+            | CustomError::ResizeError
+            | CustomError::ImageEncodeError
+            | CustomError::ProcessingError(_) => Status::InternalServerError,
+        }
+    }
 }
 
 impl fmt::Display for CustomError {
@@ -406,7 +1512,12 @@ impl fmt::Display for CustomError {
                     "Unsupported format. Use 'avif', 'heic', 'jxl', or 'qoi'."
                 )
             }
-            CustomError::DownloadError => write!(f, "Failed to download image from URL."),
+            CustomError::DownloadError {
+                upstream_status: Some(status),
+            } => write!(f, "Failed to download image from URL: upstream returned {status}."),
+            CustomError::DownloadError {
+                upstream_status: None,
+            } => write!(f, "Failed to download image from URL."),
             CustomError::ImageDecodeError => {
                 write!(f, "Failed to decode image. May be corrupt or unsupported.")
             }
@@ -424,6 +1535,10 @@ impl fmt::Display for CustomError {
             CustomError::JsonDeserializeError(details) => {
                 write!(f, "Bad request: invalid JSON - {}", details)
             }
+            #[cfg(feature = "video")]
+            CustomError::VideoDecodeError => {
+                write!(f, "Failed to decode a video frame. May be corrupt or unsupported.")
+            }
         }
     }
 }
@@ -436,7 +1551,10 @@ impl From<CustomError> for SerdeJsonValue {
 
 impl<'r> Responder<'r, 'static> for CustomError {
     fn respond_to(self, request: &'r Request) -> Result<Response<'static>, Status> {
-        SerdeJsonValue::from(self).respond_to(request)
+        let status = self.status();
+        let mut response = SerdeJsonValue::from(self).respond_to(request)?;
+        response.set_status(status);
+        Ok(response)
     }
 }
 
@@ -448,6 +1566,15 @@ impl From<serde_json::Error> for CustomError {
 
 #[launch]
 fn rocket() -> _ {
-    env_logger::init();
-    rocket::build().mount("/", routes![resize_handler])
+    tracing_subscriber::fmt::init();
+    rocket::build().mount(
+        "/",
+        routes![
+            resize_handler,
+            blob_handler,
+            blob_variant_handler,
+            metrics_handler,
+            formats_handler
+        ],
+    )
 }